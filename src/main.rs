@@ -3,8 +3,10 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
+use std::thread;
 
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -16,24 +18,71 @@ enum TransactionType {
     Chargeback,
 }
 
+/// A transaction row as it comes off the wire, before amount validation.
 #[derive(Debug, Deserialize)]
-struct Transaction {
+struct TransactionRecord {
     #[serde(rename = "type")]
     tx_type: TransactionType,
     client: u16,
     tx: u32,
     #[serde(default, deserialize_with = "deserialize_decimal")]
-    amount: Decimal,
+    amount: Option<Decimal>,
 }
 
-fn deserialize_decimal<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+fn deserialize_decimal<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
 where
     D: Deserializer<'de>,
 {
     let s: Option<&str> = Option::deserialize(deserializer)?;
     match s {
-        Some("") | None => Ok(Decimal::new(0, 4)),
-        Some(value) => value.parse::<Decimal>().map_err(serde::de::Error::custom),
+        Some("") | None => Ok(None),
+        Some(value) => value
+            .parse::<Decimal>()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// A validated, in-memory transaction ready for the engine.
+///
+/// Constructed from a [`TransactionRecord`] via [`TryFrom`], which enforces
+/// that deposits/withdrawals carry a strictly positive amount and normalizes
+/// their amounts to four fractional places. Any amount given on a
+/// dispute/resolve/chargeback is not checked; it is discarded and zero is
+/// stored.
+#[derive(Debug)]
+struct Transaction {
+    tx_type: TransactionType,
+    client: u16,
+    tx: u32,
+    amount: Decimal,
+}
+
+impl TryFrom<TransactionRecord> for Transaction {
+    type Error = ProcessError;
+
+    fn try_from(record: TransactionRecord) -> Result<Self, Self::Error> {
+        let amount = match record.tx_type {
+            TransactionType::Deposit | TransactionType::Withdrawal => {
+                let amount = record.amount.ok_or(ProcessError::MissingAmount)?;
+                if amount <= Decimal::ZERO {
+                    return Err(ProcessError::AmountNotPositive);
+                }
+                let mut amount = amount.round_dp(4);
+                amount.rescale(4);
+                amount
+            }
+            TransactionType::Dispute | TransactionType::Resolve | TransactionType::Chargeback => {
+                Decimal::ZERO
+            }
+        };
+
+        Ok(Transaction {
+            tx_type: record.tx_type,
+            client: record.client,
+            tx: record.tx,
+            amount,
+        })
     }
 }
 
@@ -65,6 +114,7 @@ fn serialize_decimal<S>(decimal: &Decimal, serializer: S) -> Result<S::Ok, S::Er
 where
     S: Serializer,
 {
+    let decimal = decimal.round_dp(4);
     if decimal.is_zero() {
         serializer.serialize_i32(0)
     } else {
@@ -72,15 +122,77 @@ where
     }
 }
 
+/// Errors that can occur while applying a single transaction to the engine.
+///
+/// These mirror the failure modes of the external ledger/bank processors:
+/// every rejection is reported rather than silently dropped, so the caller
+/// can decide whether to log, retry, or surface the row to an operator.
+#[derive(Debug, PartialEq)]
+enum ProcessError {
+    /// The account has been locked by a prior chargeback.
+    AccountLocked,
+    /// A withdrawal asked for more than the account's `available` funds.
+    InsufficientFunds,
+    /// A dispute, resolve, or chargeback referenced a `tx` that does not exist
+    /// for the same client.
+    UnknownTransaction,
+    /// A deposit or withdrawal reused a `tx` id already recorded for the same
+    /// client.
+    DuplicateTxId,
+    /// A deposit or withdrawal was missing its `amount` field.
+    MissingAmount,
+    /// A deposit or withdrawal had a zero or negative `amount`.
+    AmountNotPositive,
+    /// A dispute targeted a transaction that is already under dispute.
+    AlreadyDisputed,
+    /// A resolve or chargeback targeted a transaction that is not disputed.
+    TransactionNotDisputed,
+    /// A dispute, resolve, or chargeback targeted a transaction that has
+    /// already reached a terminal state (`Resolved` or `ChargedBack`).
+    TransactionFinalized,
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::AccountLocked => write!(f, "account is locked"),
+            ProcessError::InsufficientFunds => write!(f, "insufficient available funds"),
+            ProcessError::UnknownTransaction => write!(f, "transaction not found"),
+            ProcessError::DuplicateTxId => write!(f, "transaction id already exists"),
+            ProcessError::MissingAmount => write!(f, "amount is required"),
+            ProcessError::AmountNotPositive => write!(f, "amount must be positive"),
+            ProcessError::AlreadyDisputed => write!(f, "transaction is already disputed"),
+            ProcessError::TransactionNotDisputed => write!(f, "transaction is not disputed"),
+            ProcessError::TransactionFinalized => write!(f, "transaction has already been resolved or charged back"),
+        }
+    }
+}
+
+impl Error for ProcessError {}
+
+/// The lifecycle of a processed transaction.
+///
+/// A transaction starts `Processed`. From there it may move to `Disputed`,
+/// and a disputed transaction may move on to either `Resolved` or
+/// `ChargedBack`. `Resolved` and `ChargedBack` are terminal: once reached,
+/// no further dispute/resolve/chargeback is accepted for that `tx`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TxState {
+    Processed,
+    Disputed,
+    Resolved,
+    ChargedBack,
+}
+
 #[derive(Debug)]
 struct TransactionDetails {
     amount: Decimal,
-    disputed: bool,
+    state: TxState,
 }
 
 struct TransactionEngine {
     accounts: HashMap<u16, Account>,
-    transactions: HashMap<u32, TransactionDetails>,
+    transactions: HashMap<(u16, u32), TransactionDetails>,
 }
 
 impl TransactionEngine {
@@ -91,10 +203,10 @@ impl TransactionEngine {
         }
     }
 
-    fn process_transaction(&mut self, transaction: Transaction) {
+    fn process_transaction(&mut self, transaction: Transaction) -> Result<(), ProcessError> {
         if let Some(account) = self.accounts.get(&transaction.client) {
             if account.locked {
-                return;
+                return Err(ProcessError::AccountLocked);
             }
         }
 
@@ -107,91 +219,245 @@ impl TransactionEngine {
         }
     }
 
-    fn handle_deposit(&mut self, transaction: Transaction) {
-        let account = self.accounts.entry(transaction.client)
+    fn handle_deposit(&mut self, transaction: Transaction) -> Result<(), ProcessError> {
+        if self.transactions.contains_key(&(transaction.client, transaction.tx)) {
+            return Err(ProcessError::DuplicateTxId);
+        }
+        let account = self
+            .accounts
+            .entry(transaction.client)
             .or_insert(Account::new(transaction.client));
         account.available += transaction.amount;
         account.total += transaction.amount;
-        self.transactions.insert(transaction.tx, TransactionDetails {
-            amount: transaction.amount,
-            disputed: false,
-        });
+        self.transactions.insert(
+            (transaction.client, transaction.tx),
+            TransactionDetails {
+                amount: transaction.amount,
+                state: TxState::Processed,
+            },
+        );
+        Ok(())
     }
 
-    fn handle_withdrawal(&mut self, transaction: Transaction) {
-        let account = self.accounts.entry(transaction.client)
+    fn handle_withdrawal(&mut self, transaction: Transaction) -> Result<(), ProcessError> {
+        if self.transactions.contains_key(&(transaction.client, transaction.tx)) {
+            return Err(ProcessError::DuplicateTxId);
+        }
+        let account = self
+            .accounts
+            .entry(transaction.client)
             .or_insert(Account::new(transaction.client));
-        if account.available >= transaction.amount {
-            account.available -= transaction.amount;
-            account.total -= transaction.amount;
-            self.transactions.insert(transaction.tx, TransactionDetails {
-                amount: transaction.amount,
-                disputed: false,
-            });
+        if account.available < transaction.amount {
+            return Err(ProcessError::InsufficientFunds);
         }
+        account.available -= transaction.amount;
+        account.total -= transaction.amount;
+        self.transactions.insert(
+            (transaction.client, transaction.tx),
+            TransactionDetails {
+                amount: transaction.amount,
+                state: TxState::Processed,
+            },
+        );
+        Ok(())
     }
 
-    fn handle_dispute(&mut self, transaction: Transaction) {
-        let account = self.accounts.entry(transaction.client)
+    fn handle_dispute(&mut self, transaction: Transaction) -> Result<(), ProcessError> {
+        let account = self
+            .accounts
+            .entry(transaction.client)
             .or_insert(Account::new(transaction.client));
-        if let Some(transaction_details) = self.transactions.get_mut(&transaction.tx) {
-            if !transaction_details.disputed && account.available >= transaction_details.amount {
-                transaction_details.disputed = true;
-                account.available -= transaction_details.amount;
-                account.held += transaction_details.amount;
-            }
+        let transaction_details = self
+            .transactions
+            .get_mut(&(transaction.client, transaction.tx))
+            .ok_or(ProcessError::UnknownTransaction)?;
+        match transaction_details.state {
+            TxState::Disputed => return Err(ProcessError::AlreadyDisputed),
+            TxState::Resolved | TxState::ChargedBack => return Err(ProcessError::TransactionFinalized),
+            TxState::Processed => {}
         }
+        // The disputed funds move from available to held unconditionally, even
+        // if the client has since withdrawn them, so available may go negative
+        // until a resolve or chargeback settles it.
+        transaction_details.state = TxState::Disputed;
+        account.available -= transaction_details.amount;
+        account.held += transaction_details.amount;
+        Ok(())
     }
 
-    fn handle_resolve(&mut self, transaction: Transaction) {
-        let account = self.accounts.entry(transaction.client)
+    fn handle_resolve(&mut self, transaction: Transaction) -> Result<(), ProcessError> {
+        let account = self
+            .accounts
+            .entry(transaction.client)
             .or_insert(Account::new(transaction.client));
-        if let Some(transaction_details) = self.transactions.get_mut(&transaction.tx) {
-            if transaction_details.disputed && account.held >= transaction_details.amount {
-                transaction_details.disputed = false;
-                account.available += transaction_details.amount;
-                account.held -= transaction_details.amount;
-                self.transactions.remove(&transaction.tx);
-            }
+        let transaction_details = self
+            .transactions
+            .get_mut(&(transaction.client, transaction.tx))
+            .ok_or(ProcessError::UnknownTransaction)?;
+        match transaction_details.state {
+            TxState::Disputed => {}
+            TxState::Resolved | TxState::ChargedBack => return Err(ProcessError::TransactionFinalized),
+            TxState::Processed => return Err(ProcessError::TransactionNotDisputed),
         }
+        transaction_details.state = TxState::Resolved;
+        account.available += transaction_details.amount;
+        account.held -= transaction_details.amount;
+        Ok(())
     }
 
-    fn handle_chargeback(&mut self, transaction: Transaction) {
-        let account = self.accounts.entry(transaction.client)
+    fn handle_chargeback(&mut self, transaction: Transaction) -> Result<(), ProcessError> {
+        let account = self
+            .accounts
+            .entry(transaction.client)
             .or_insert(Account::new(transaction.client));
-        if let Some(transaction_details) = self.transactions.get_mut(&transaction.tx) {
-            if transaction_details.disputed && account.held >= transaction_details.amount {
-                transaction_details.disputed = false;
-                account.held -= transaction_details.amount;
-                account.total -= transaction_details.amount;
-                account.locked = true;
-                self.transactions.remove(&transaction.tx);
+        let transaction_details = self
+            .transactions
+            .get_mut(&(transaction.client, transaction.tx))
+            .ok_or(ProcessError::UnknownTransaction)?;
+        match transaction_details.state {
+            TxState::Disputed => {}
+            TxState::Resolved | TxState::ChargedBack => return Err(ProcessError::TransactionFinalized),
+            TxState::Processed => return Err(ProcessError::TransactionNotDisputed),
+        }
+        transaction_details.state = TxState::ChargedBack;
+        account.held -= transaction_details.amount;
+        account.total -= transaction_details.amount;
+        account.locked = true;
+        Ok(())
+    }
+}
+
+/// Parsed command line arguments: the input file and an optional shard count.
+struct Args {
+    input_path: String,
+    threads: usize,
+}
+
+fn parse_args(raw: Vec<String>) -> Result<Args, Box<dyn Error>> {
+    let usage = "Usage: cargo run -- <input_file.csv> [--threads N]";
+    let mut input_path = None;
+    let mut threads = 1;
+
+    let mut raw = raw.into_iter().skip(1);
+    while let Some(arg) = raw.next() {
+        match arg.as_str() {
+            "--threads" => {
+                let value = raw.next().ok_or(usage)?;
+                threads = value.parse::<usize>()?;
+                if threads == 0 {
+                    return Err("--threads must be at least 1".into());
+                }
             }
+            _ if input_path.is_none() => input_path = Some(arg),
+            _ => return Err(usage.into()),
         }
     }
+
+    Ok(Args {
+        input_path: input_path.ok_or(usage)?,
+        threads,
+    })
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        return Err("Usage: cargo run -- <input_file.csv>".into());
+type CsvReader = csv::Reader<BufReader<File>>;
+
+/// Processes the whole stream on the current thread, the default path.
+fn run_single_threaded(mut reader: CsvReader) -> Result<HashMap<u16, Account>, Box<dyn Error>> {
+    let mut engine = TransactionEngine::new();
+    for result in reader.deserialize() {
+        let record: TransactionRecord = result?;
+        let client = record.client;
+        let tx = record.tx;
+        let transaction = match Transaction::try_from(record) {
+            Ok(transaction) => transaction,
+            Err(err) => {
+                eprintln!("rejected tx {} for client {}: {}", tx, client, err);
+                continue;
+            }
+        };
+        if let Err(err) = engine.process_transaction(transaction) {
+            eprintln!("rejected tx {} for client {}: {}", tx, client, err);
+        }
     }
+    Ok(engine.accounts)
+}
+
+/// Partitions the stream across `threads` shards by `client % threads`.
+///
+/// Every account and every dispute/resolve/chargeback is scoped to a single
+/// `client`, so each shard owns a disjoint set of clients and can run its
+/// own `TransactionEngine` with no shared state or locking. Per-shard
+/// ordering is preserved because each client's transactions are always sent
+/// to the same shard's channel in the order they were read.
+fn run_sharded(mut reader: CsvReader, threads: usize) -> Result<HashMap<u16, Account>, Box<dyn Error>> {
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..threads)
+        .map(|_| std::sync::mpsc::sync_channel::<Transaction>(1024))
+        .unzip();
+
+    let mut accounts = HashMap::new();
+    thread::scope(|scope| -> Result<(), Box<dyn Error>> {
+        let handles: Vec<_> = receivers
+            .into_iter()
+            .map(|receiver| {
+                scope.spawn(move || {
+                    let mut engine = TransactionEngine::new();
+                    for transaction in receiver {
+                        let client = transaction.client;
+                        let tx = transaction.tx;
+                        if let Err(err) = engine.process_transaction(transaction) {
+                            eprintln!("rejected tx {} for client {}: {}", tx, client, err);
+                        }
+                    }
+                    engine.accounts
+                })
+            })
+            .collect();
+
+        for result in reader.deserialize() {
+            let record: TransactionRecord = result?;
+            let client = record.client;
+            let tx = record.tx;
+            let transaction = match Transaction::try_from(record) {
+                Ok(transaction) => transaction,
+                Err(err) => {
+                    eprintln!("rejected tx {} for client {}: {}", tx, client, err);
+                    continue;
+                }
+            };
+            let shard = transaction.client as usize % threads;
+            senders[shard]
+                .send(transaction)
+                .expect("shard worker thread terminated unexpectedly");
+        }
+        drop(senders);
+
+        for handle in handles {
+            let shard_accounts = handle.join().expect("shard worker thread panicked");
+            accounts.extend(shard_accounts);
+        }
+        Ok(())
+    })?;
 
+    Ok(accounts)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = parse_args(std::env::args().collect())?;
 
-    let input_file = File::open(&args[1])?;
-    let mut reader = ReaderBuilder::new()
+    let input_file = File::open(&args.input_path)?;
+    let reader = ReaderBuilder::new()
         .trim(csv::Trim::All)
         .flexible(true)
         .from_reader(BufReader::new(input_file));
 
-    let mut engine = TransactionEngine::new();
-
-    for result in reader.deserialize() {
-        engine.process_transaction(result?);
-    }
+    let accounts = if args.threads > 1 {
+        run_sharded(reader, args.threads)?
+    } else {
+        run_single_threaded(reader)?
+    };
 
     let mut writer = WriterBuilder::new().from_writer(std::io::stdout());
-    for (i, account) in engine.accounts.values().enumerate() {
+    for (i, account) in accounts.values().enumerate() {
         writer.serialize(account)?;
         if i % 100 == 0 {
             writer.flush()?;
@@ -222,7 +488,12 @@ chargeback,1,1,
             .flexible(true)
             .from_reader(Cursor::new(input));
 
-        let transactions: Vec<Transaction> = reader.deserialize().collect::<Result<Vec<_>, _>>().unwrap();
+        let records: Vec<TransactionRecord> = reader.deserialize().collect::<Result<Vec<_>, _>>().unwrap();
+        let transactions: Vec<Transaction> = records
+            .into_iter()
+            .map(Transaction::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
 
         assert_eq!(transactions.len(), 5);
         assert_eq!(transactions[0].tx_type, TransactionType::Deposit);
@@ -273,178 +544,338 @@ chargeback,1,1,
         let mut transaction_engine = TransactionEngine::new();
 
         // make some deposits
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Deposit,
-            client: 1,
-            tx: 1,
-            amount: Decimal::new(10, 0),
-        });
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 1,
+                tx: 1,
+                amount: Decimal::new(10, 0),
+            })
+            .unwrap();
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(10, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(10, 0));
-        assert_eq!(transaction_engine.transactions.get(&1).unwrap().amount, Decimal::new(10, 0));
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Deposit,
-            client: 2,
-            tx: 2,
-            amount: Decimal::new(20, 0),
-        });
+        assert_eq!(transaction_engine.transactions.get(&(1, 1)).unwrap().amount, Decimal::new(10, 0));
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 2,
+                tx: 2,
+                amount: Decimal::new(20, 0),
+            })
+            .unwrap();
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().available, Decimal::new(20, 0));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().total, Decimal::new(20, 0));
-        assert_eq!(transaction_engine.transactions.get(&2).unwrap().amount, Decimal::new(20, 0));
+        assert_eq!(transaction_engine.transactions.get(&(2, 2)).unwrap().amount, Decimal::new(20, 0));
 
         // withdraw successfully
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Withdrawal,
-            client: 1,
-            tx: 3,
-            amount: Decimal::new(1, 0),
-        });
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Withdrawal,
+                client: 1,
+                tx: 3,
+                amount: Decimal::new(1, 0),
+            })
+            .unwrap();
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(9, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
-        assert_eq!(transaction_engine.transactions.get(&3).unwrap().amount, Decimal::new(1, 0));
+        assert_eq!(transaction_engine.transactions.get(&(1, 3)).unwrap().amount, Decimal::new(1, 0));
 
         // withdraw unsuccessfully
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Withdrawal,
-            client: 1,
-            tx: 4,
-            amount: Decimal::new(100, 0),
-        });
-        // since the account has only 10 available, the withdrawal should not be processed
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Withdrawal,
+                client: 1,
+                tx: 4,
+                amount: Decimal::new(100, 0),
+            })
+            .unwrap_err();
+        // since the account has only 9 available, the withdrawal should not be processed
+        assert_eq!(err, ProcessError::InsufficientFunds);
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(9, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
-        assert!(transaction_engine.transactions.get(&4).is_none());
+        assert!(!transaction_engine.transactions.contains_key(&(1, 4)));
 
         // dispute successfully
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Dispute,
-            client: 1,
-            tx: 3,
-            amount: Decimal::new(0, 1),
-        });
-        assert!(transaction_engine.transactions.get(&3).unwrap().disputed);
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 3,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap();
+        assert_eq!(transaction_engine.transactions.get(&(1, 3)).unwrap().state, TxState::Disputed);
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(8, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(1, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
 
         // dispute already disputed transaction
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Dispute,
-            client: 1,
-            tx: 3,
-            amount: Decimal::new(0, 1),
-        });
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 3,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap_err();
         // nothing changes
-        assert!(transaction_engine.transactions.get(&3).unwrap().disputed);
+        assert_eq!(err, ProcessError::AlreadyDisputed);
+        assert_eq!(transaction_engine.transactions.get(&(1, 3)).unwrap().state, TxState::Disputed);
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(8, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(1, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
 
         // dispute non-existent transaction
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Dispute,
-            client: 1,
-            tx: 5,
-            amount: Decimal::new(0, 1),
-        });
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 5,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap_err();
         // nothing changes
-        assert!(transaction_engine.transactions.get(&6).is_none());
+        assert_eq!(err, ProcessError::UnknownTransaction);
+        assert!(!transaction_engine.transactions.contains_key(&(1, 5)));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(8, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(1, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
 
 
         // resolve successfully
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Resolve,
-            client: 1,
-            tx: 3,
-            amount: Decimal::new(0, 1),
-        });
-        assert!(transaction_engine.transactions.get(&3).is_none());
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Resolve,
+                client: 1,
+                tx: 3,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap();
+        assert_eq!(transaction_engine.transactions.get(&(1, 3)).unwrap().state, TxState::Resolved);
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(9, 0));
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
+
+        // re-dispute a resolved transaction is rejected, since it is terminal
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 3,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap_err();
+        assert_eq!(err, ProcessError::TransactionFinalized);
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(9, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
 
         // resolve unsuccessfully, un-disputed transaction
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Resolve,
-            client: 2,
-            tx: 2,
-            amount: Decimal::new(0, 1),
-        });
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Resolve,
+                client: 2,
+                tx: 2,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap_err();
         // nothing changes
-        assert!(!transaction_engine.transactions.get(&2).unwrap().disputed);
+        assert_eq!(err, ProcessError::TransactionNotDisputed);
+        assert_eq!(transaction_engine.transactions.get(&(2, 2)).unwrap().state, TxState::Processed);
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().available, Decimal::new(20, 0));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().total, Decimal::new(20, 0));
 
         // chargeback successfully
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Withdrawal,
-            client: 2,
-            tx: 4,
-            amount: Decimal::new(5, 0),
-        });
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Dispute,
-            client: 2,
-            tx: 4,
-            amount: Decimal::new(0, 1),
-        });
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Chargeback,
-            client: 2,
-            tx: 4,
-            amount: Decimal::new(0, 1),
-        });
-        assert!(transaction_engine.transactions.get(&4).is_none());
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Withdrawal,
+                client: 2,
+                tx: 4,
+                amount: Decimal::new(5, 0),
+            })
+            .unwrap();
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 2,
+                tx: 4,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap();
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Chargeback,
+                client: 2,
+                tx: 4,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap();
+        assert_eq!(transaction_engine.transactions.get(&(2, 4)).unwrap().state, TxState::ChargedBack);
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().available, Decimal::new(10, 0));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().total, Decimal::new(10, 0));
         assert!(transaction_engine.accounts.get(&2).unwrap().locked);
 
         // chargeback unsuccessfully, non-existent transaction
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Chargeback,
-            client: 1,
-            tx: 6,
-            amount: Decimal::new(0, 1),
-        });
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Chargeback,
+                client: 1,
+                tx: 6,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap_err();
         // nothing changes
+        assert_eq!(err, ProcessError::UnknownTransaction);
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(9, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
 
         // chargeback unsuccessfully, non-disputed transaction
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Chargeback,
-            client: 1,
-            tx: 1,
-            amount: Decimal::new(0, 1),
-        });
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Chargeback,
+                client: 1,
+                tx: 1,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap_err();
         // nothing changes
+        assert_eq!(err, ProcessError::TransactionNotDisputed);
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(9, 0));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(9, 0));
 
         // ignored transaction on locked account
-        transaction_engine.process_transaction(Transaction {
-            tx_type: TransactionType::Deposit,
-            client: 2,
-            tx: 5,
-            amount: Decimal::new(10, 0),
-        });
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 2,
+                tx: 5,
+                amount: Decimal::new(10, 0),
+            })
+            .unwrap_err();
         // nothing changes
+        assert_eq!(err, ProcessError::AccountLocked);
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().available, Decimal::new(10, 0));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().held, Decimal::new(0, 1));
         assert_eq!(transaction_engine.accounts.get(&2).unwrap().total, Decimal::new(10, 0));
         // transaction does not exist
-        assert!(transaction_engine.transactions.get(&5).is_none());
+        assert!(!transaction_engine.transactions.contains_key(&(2, 5)));
     }
-}
 
+    #[test]
+    fn test_dispute_scoped_to_owning_client() {
+        let mut transaction_engine = TransactionEngine::new();
+
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 1,
+                tx: 1,
+                amount: Decimal::new(10, 0),
+            })
+            .unwrap();
+
+        // client 2 cannot dispute client 1's transaction, even though the tx id matches
+        let err = transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 2,
+                tx: 1,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap_err();
+        assert_eq!(err, ProcessError::UnknownTransaction);
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(10, 0));
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(0, 1));
+    }
+
+    #[test]
+    fn test_dispute_can_take_available_negative() {
+        let mut transaction_engine = TransactionEngine::new();
+
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 1,
+                tx: 1,
+                amount: Decimal::new(10, 0),
+            })
+            .unwrap();
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Withdrawal,
+                client: 1,
+                tx: 2,
+                amount: Decimal::new(10, 0),
+            })
+            .unwrap();
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(0, 0));
+
+        // the deposit is disputed after the funds have already been withdrawn
+        transaction_engine
+            .process_transaction(Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 1,
+                amount: Decimal::new(0, 1),
+            })
+            .unwrap();
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().available, Decimal::new(-10, 0));
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().held, Decimal::new(10, 0));
+        assert_eq!(transaction_engine.accounts.get(&1).unwrap().total, Decimal::new(0, 0));
+    }
+
+    #[test]
+    fn test_transaction_try_from_validates_amount() {
+        let missing = TransactionRecord {
+            tx_type: TransactionType::Deposit,
+            client: 1,
+            tx: 1,
+            amount: None,
+        };
+        assert_eq!(Transaction::try_from(missing).unwrap_err(), ProcessError::MissingAmount);
+
+        let zero = TransactionRecord {
+            tx_type: TransactionType::Withdrawal,
+            client: 1,
+            tx: 2,
+            amount: Some(Decimal::new(0, 0)),
+        };
+        assert_eq!(Transaction::try_from(zero).unwrap_err(), ProcessError::AmountNotPositive);
+
+        let negative = TransactionRecord {
+            tx_type: TransactionType::Deposit,
+            client: 1,
+            tx: 3,
+            amount: Some(Decimal::new(-10, 1)),
+        };
+        assert_eq!(Transaction::try_from(negative).unwrap_err(), ProcessError::AmountNotPositive);
+
+        // amounts are normalized to exactly 4 fractional places
+        let precise = TransactionRecord {
+            tx_type: TransactionType::Deposit,
+            client: 1,
+            tx: 4,
+            amount: Some("2.74219".parse().unwrap()),
+        };
+        let transaction = Transaction::try_from(precise).unwrap();
+        assert_eq!(transaction.amount, Decimal::new(27422, 4));
+
+        // dispute/resolve/chargeback never carry an amount
+        let dispute = TransactionRecord {
+            tx_type: TransactionType::Dispute,
+            client: 1,
+            tx: 5,
+            amount: None,
+        };
+        let transaction = Transaction::try_from(dispute).unwrap();
+        assert_eq!(transaction.amount, Decimal::ZERO);
+    }
+}